@@ -1,14 +1,37 @@
-use std::{path::PathBuf, sync::LazyLock, usize};
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+    usize,
+};
+use std::sync::LazyLock;
 
 use clap::{CommandFactory, Parser};
 use glob::glob;
 
 static USIZE_MAX_STR: LazyLock<String> = LazyLock::new(|| usize::MAX.to_string());
 
+/// Output format for a listing: human-oriented text, a JSON array, or JSON Lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    Jsonl,
+}
+
 /// A program to mimic the `dir' windows command
-#[derive(Parser, Debug)]
-#[command(version, author, about, long_about = None)]
+#[derive(Parser, Debug, Clone)]
+#[command(version, author, about, long_about = None, disable_help_flag = true)]
 struct Args {
+    /// Print help
+    #[arg(long, action = clap::ArgAction::Help)]
+    help: Option<bool>,
     #[cfg(feature = "mangen")]
     #[arg(long)]
     mangen: bool,
@@ -33,6 +56,27 @@ struct Args {
     /// No color output
     #[arg(short, long)]
     raw: bool,
+    /// Number of worker threads to use for recursive traversal
+    #[arg(short = 'j', long, default_value = "1")]
+    jobs: usize,
+    /// Order entries like `dir /O`: name, size, ext or date, with an optional leading '-' for descending
+    #[arg(short, long, allow_hyphen_values = true)]
+    order: Option<String>,
+    /// Format sizes as human-readable binary units (e.g. 1.4 MiB) instead of raw bytes
+    #[arg(short = 'h', long = "human")]
+    human: bool,
+    /// Show each directory's total recursive size next to its `<DIR>` line
+    #[arg(long)]
+    du: bool,
+    /// Output format: plain text, a JSON array, or JSON Lines
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Follow directory symlinks during recursion, guarding against cycles
+    #[arg(short = 'L', long = "follow")]
+    follow: bool,
+    /// Drop into an interactive shell for browsing the listing
+    #[arg(long)]
+    shell: bool,
 }
 
 fn main() {
@@ -53,13 +97,19 @@ fn main() {
         arg_error("path".to_string(), args.path);
     }
 
+    if let Some(order) = &args.order {
+        if parse_order(order).is_none() {
+            arg_error("order".to_string(), order.clone());
+        }
+    }
+
     if args.quiet && args.bare {
         return;
     }
 
     let directories_only;
     let file_os;
-    
+
     if args.file.ends_with('.') {
         directories_only = true;
         let mut new_file = args.file.clone();
@@ -72,9 +122,14 @@ fn main() {
 
     let path_os = PathBuf::from(args.path.clone());
 
+    if args.shell {
+        run_shell(&args, path_os, file_os, directories_only);
+        return;
+    }
+
     let stats = dir_cmd_recursive(&args, path_os, &file_os, directories_only, args.depth);
-    if !args.bare {
-        print_end_stats(stats.0, stats.1, stats.2);
+    if !args.bare && args.format == OutputFormat::Text {
+        print_end_stats(stats.0, stats.1, stats.2, args.human);
     }
 }
 
@@ -85,17 +140,347 @@ fn arg_error(arg: String, value: String) -> ! {
     err.exit();
 }
 
+/// Identifies a directory uniquely enough to detect symlink cycles: the
+/// `(device, inode)` pair where available, falling back to the canonical
+/// path on platforms without inode access.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+enum DirId {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    Inode(u64, u64),
+    #[cfg_attr(unix, allow(dead_code))]
+    Path(PathBuf),
+}
+
+fn dir_identity(path: &Path) -> Option<DirId> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = path.metadata().ok()?;
+        Some(DirId::Inode(metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        path.canonicalize().ok().map(DirId::Path)
+    }
+}
+
+/// State shared by every worker thread in the traversal pool: the totals
+/// accumulator, stdout's lock, the pending-item counter used to detect a
+/// drained queue, the set of every directory identity visited so far
+/// (seeded with the traversal root, for `--follow` cycle detection), and
+/// (in `--format json`) the buffer of serialized entries.
+struct PoolState {
+    stdout_lock: Mutex<()>,
+    files: AtomicUsize,
+    file_size_sum: AtomicUsize,
+    directories: AtomicUsize,
+    pending: AtomicUsize,
+    done: AtomicUsize,
+    json_entries: Mutex<Vec<String>>,
+    visited: Mutex<HashSet<DirId>>,
+}
+
+impl PoolState {
+    fn new() -> Self {
+        PoolState {
+            stdout_lock: Mutex::new(()),
+            files: AtomicUsize::new(0),
+            file_size_sum: AtomicUsize::new(0),
+            directories: AtomicUsize::new(0),
+            pending: AtomicUsize::new(1),
+            done: AtomicUsize::new(0),
+            json_entries: Mutex::new(Vec::new()),
+            visited: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+/// The recursion budget remaining (counted down from `-s`) alongside the
+/// nesting level from the traversal root (counted up from 0), threaded
+/// together through the worker pool so each can be used for what it's
+/// actually for: `remaining` to decide whether to keep recursing, `level`
+/// to report an entry's real depth in `--format json`/`jsonl`.
+#[derive(Clone, Copy)]
+struct Depth {
+    remaining: usize,
+    level: usize,
+}
+
+/// Drives the worker thread pool over the shared path stack and folds the
+/// per-directory results into the totals returned to `main`. Subdirectories
+/// are pushed onto a shared LIFO stack rather than a FIFO queue, so a worker
+/// that just discovered a subtree keeps recursing into it (matching the
+/// grouped-by-subtree order `dir /s` has always produced) instead of
+/// draining unrelated siblings first.
 fn dir_cmd_recursive(args: &Args, current_path: PathBuf, file_pattern: &PathBuf, directories_only: bool, depth: usize) -> (usize, usize, usize) {
-    let mut files = 0;
-    let mut file_size_sum = 0;
-    let mut directories = 0;
+    let state = Arc::new(PoolState::new());
+    if let Some(id) = dir_identity(&current_path) {
+        state.visited.lock().unwrap().insert(id);
+    }
+    let stack = Arc::new(Mutex::new(vec![(current_path, Depth { remaining: depth, level: 0 })]));
+
+    let jobs = args.jobs.max(1);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let stack = Arc::clone(&stack);
+            let state = Arc::clone(&state);
+
+            scope.spawn(move || loop {
+                if state.done.load(Ordering::SeqCst) != 0 {
+                    break;
+                }
+
+                let item = stack.lock().unwrap().pop();
+
+                match item {
+                    Some((path, depth)) => {
+                        visit_directory(args, path, file_pattern, directories_only, depth, &stack, &state);
+
+                        if state.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                            state.done.store(1, Ordering::SeqCst);
+                        }
+                    }
+                    None => thread::sleep(Duration::from_millis(10)),
+                }
+            });
+        }
+    });
+
+    let total_files = state.files.load(Ordering::SeqCst);
+    let total_bytes = state.file_size_sum.load(Ordering::SeqCst);
+    let total_dirs = state.directories.load(Ordering::SeqCst);
+
+    match args.format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let entries = state.json_entries.lock().unwrap();
+            let mut body = format!("[{}", entries.join(","));
+            if !args.bare {
+                if !entries.is_empty() {
+                    body.push(',');
+                }
+                body.push_str(&json_summary(total_files, total_bytes, total_dirs));
+            }
+            body.push(']');
+            println!("{body}");
+        }
+        OutputFormat::Jsonl => {
+            if !args.bare {
+                println!("{}", json_summary(total_files, total_bytes, total_dirs));
+            }
+        }
+    }
 
-    let glob = match glob(current_path.clone().join(file_pattern.clone()).to_str().unwrap()) {
+    (total_files, total_bytes, total_dirs)
+}
+
+/// A single glob match gathered before sorting/printing, holding just enough
+/// metadata to order by any of the supported `--order` keys.
+struct Entry {
+    path: PathBuf,
+    is_dir: bool,
+    size: u64,
+    mtime: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Size,
+    Ext,
+    Date,
+}
+
+/// Parses an `--order` value such as `size` or `-date` into a key and
+/// whether it requests descending order.
+fn parse_order(raw: &str) -> Option<(SortKey, bool)> {
+    let (descending, key) = match raw.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, raw),
+    };
+    let key = match key {
+        "name" => SortKey::Name,
+        "size" => SortKey::Size,
+        "ext" => SortKey::Ext,
+        "date" => SortKey::Date,
+        _ => return None,
+    };
+    Some((key, descending))
+}
+
+fn compare_entries(a: &Entry, b: &Entry, key: SortKey) -> std::cmp::Ordering {
+    match key {
+        SortKey::Name => a.path.file_name().cmp(&b.path.file_name()),
+        SortKey::Size => a.size.cmp(&b.size),
+        SortKey::Ext => a.path.extension().cmp(&b.path.extension()),
+        SortKey::Date => a.mtime.cmp(&b.mtime),
+    }
+}
+
+/// Sorts entries by the requested key, grouping directories first when
+/// sorting by `size` or `date` so totals aren't interleaved with files.
+fn sort_entries(entries: &mut [Entry], order: Option<(SortKey, bool)>) {
+    let Some((key, descending)) = order else {
+        return;
+    };
+
+    let group_dirs = matches!(key, SortKey::Size | SortKey::Date);
+
+    entries.sort_by(|a, b| {
+        if group_dirs {
+            let group = b.is_dir.cmp(&a.is_dir);
+            if group != std::cmp::Ordering::Equal {
+                return group;
+            }
+        }
+
+        let ordering = compare_entries(a, b, key);
+        if descending { ordering.reverse() } else { ordering }
+    });
+}
+
+/// Formats a byte count as `1.4 MiB`-style binary units, or as raw bytes
+/// when `human` is false.
+fn format_size(bytes: usize, human: bool) -> String {
+    if !human {
+        return format!("{} bytes", bytes);
+    }
+
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Sums the bytes of files under `path`, at any depth, that match
+/// `file_pattern` and, unless `--all` is set, are not hidden; the `-s` depth
+/// limit of the listing does not apply to this total. Subdirectories are always descended into regardless of the pattern, since
+/// that's how the main traversal keeps listing their own matching children.
+/// Seeds a fresh cycle guard with `path`'s own identity, then mirrors
+/// `visit_directory`'s `--follow`/visited-identity handling so a `--du`
+/// total for a followed symlinked directory doesn't loop forever or go
+/// inconsistent with the run's other totals.
+fn subtree_size(args: &Args, file_pattern: &Path, path: &Path) -> usize {
+    let mut visited = HashSet::new();
+    if let Some(id) = dir_identity(path) {
+        visited.insert(id);
+    }
+    subtree_size_inner(args, file_pattern, path, &mut visited)
+}
+
+fn subtree_size_inner(args: &Args, file_pattern: &Path, path: &Path, visited: &mut HashSet<DirId>) -> usize {
+    if path.is_symlink() && !args.follow {
+        return 0;
+    }
+    if path.is_file() {
+        return path.metadata().map(|m| m.len() as usize).unwrap_or(0);
+    }
+    if !path.is_dir() {
+        return 0;
+    }
+
+    let mut total = 0usize;
+
+    if let Ok(glob_iter) = glob(path.join(file_pattern).to_str().unwrap()) {
+        for entry in glob_iter.filter_map(|p| p.ok()) {
+            let Some(name) = entry.file_name() else { continue };
+            if !args.all && name.as_encoded_bytes()[0] == b'.' {
+                continue;
+            }
+            if entry.is_file() {
+                total += entry.metadata().map(|m| m.len() as usize).unwrap_or(0);
+            }
+        }
+    }
+
+    if let Ok(read_dir) = path.read_dir() {
+        for child in read_dir
+            .filter_map(Result::ok)
+            .map(|ent| ent.path())
+            .filter(|p| p.is_dir())
+            .filter(|p| args.follow || !p.is_symlink())
+            .filter(|p| args.all || p.file_name().unwrap().as_encoded_bytes()[0] != b'.')
+        {
+            let Some(id) = dir_identity(&child) else { continue };
+            if !visited.insert(id) {
+                continue;
+            }
+            total += subtree_size_inner(args, file_pattern, &child, visited);
+        }
+    }
+
+    total
+}
+
+/// Escapes a string for embedding as a JSON string literal.
+fn json_escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn json_file_entry(path: &Path, size: u64, depth: usize) -> String {
+    format!(
+        "{{\"type\":\"file\",\"path\":\"{}\",\"size\":{},\"depth\":{}}}",
+        json_escape(&path.display().to_string()),
+        size,
+        depth
+    )
+}
+
+fn json_dir_entry(path: &Path, depth: usize) -> String {
+    format!(
+        "{{\"type\":\"dir\",\"path\":\"{}\",\"depth\":{}}}",
+        json_escape(&path.display().to_string()),
+        depth
+    )
+}
+
+fn json_summary(files: usize, bytes: usize, dirs: usize) -> String {
+    format!("{{\"files\":{},\"bytes\":{},\"dirs\":{}}}", files, bytes, dirs)
+}
+
+/// Lists a single directory's entries and pushes its subdirectories (tagged
+/// with their remaining depth and their nesting level from the traversal
+/// root) onto the shared stack for the pool.
+fn visit_directory(
+    args: &Args,
+    current_path: PathBuf,
+    file_pattern: &PathBuf,
+    directories_only: bool,
+    depth: Depth,
+    stack: &Mutex<Vec<(PathBuf, Depth)>>,
+    state: &PoolState,
+) {
+    let glob_iter = match glob(current_path.join(file_pattern).to_str().unwrap()) {
         Ok(paths) => paths,
-        Err(_) =>  return (0, 0, 0),
+        Err(_) => return,
     };
 
-    for path in glob.filter_map(|p| match p { Ok(p) => Some(p), Err(_) => None }) {
+    let mut entries = Vec::new();
+
+    for path in glob_iter.filter_map(|p| p.ok()) {
         let name = match path.file_name() {
             Some(name) => name,
             None => continue,
@@ -104,44 +489,263 @@ fn dir_cmd_recursive(args: &Args, current_path: PathBuf, file_pattern: &PathBuf,
             continue;
         }
 
-
         if path.is_file() && !directories_only {
-            files += 1;
-            let file_size = path.metadata().unwrap().len() as usize;
-            file_size_sum += file_size;
+            let metadata = path.metadata().unwrap();
+            entries.push(Entry {
+                size: metadata.len(),
+                mtime: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                is_dir: false,
+                path,
+            });
+        } else if path.is_dir() {
+            let mtime = path.metadata().and_then(|m| m.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push(Entry { size: 0, mtime, is_dir: true, path });
+        }
+    }
+
+    sort_entries(&mut entries, args.order.as_deref().and_then(parse_order));
+
+    let mut out = String::new();
+    let mut local_files = 0;
+    let mut local_bytes = 0;
+    let mut local_dirs = 0;
+
+    let mut new_json_entries = Vec::new();
+
+    for entry in &entries {
+        let canonical = entry.path.canonicalize().unwrap();
+
+        if entry.is_dir {
+            local_dirs += 1;
             if !args.quiet {
-                if args.raw {
-                    println!("<FILE>\t{}\t{} bytes", path.canonicalize().unwrap().display(), file_size);
-                } else {
-                    println!("\x1b[1;32m<FILE>\x1b[0m\t{}\t{} bytes", path.canonicalize().unwrap().display(), file_size);
+                match args.format {
+                    OutputFormat::Text => {
+                        let du_suffix = if args.du {
+                            format!("\t{}", format_size(subtree_size(args, file_pattern, &entry.path), args.human))
+                        } else {
+                            String::new()
+                        };
+                        if args.raw {
+                            out.push_str(&format!("<DIR>\t{}{}\n", canonical.display(), du_suffix));
+                        } else {
+                            out.push_str(&format!("\x1b[1;35m<DIR>\x1b[0m\t{}{}\n", canonical.display(), du_suffix));
+                        }
+                    }
+                    OutputFormat::Json => new_json_entries.push(json_dir_entry(&canonical, depth.level)),
+                    OutputFormat::Jsonl => out.push_str(&format!("{}\n", json_dir_entry(&canonical, depth.level))),
                 }
             }
-        } else if path.is_dir() {
-            directories += 1;
+        } else {
+            local_files += 1;
+            local_bytes += entry.size as usize;
             if !args.quiet {
-                if args.raw {
-                    println!("<DIR>\t{}", path.canonicalize().unwrap().display());
-                } else {
-                    println!("\x1b[1;35m<DIR>\x1b[0m\t{}", path.canonicalize().unwrap().display());
+                match args.format {
+                    OutputFormat::Text => {
+                        let size = format_size(entry.size as usize, args.human);
+                        if args.raw {
+                            out.push_str(&format!("<FILE>\t{}\t{}\n", canonical.display(), size));
+                        } else {
+                            out.push_str(&format!("\x1b[1;32m<FILE>\x1b[0m\t{}\t{}\n", canonical.display(), size));
+                        }
+                    }
+                    OutputFormat::Json => new_json_entries.push(json_file_entry(&canonical, entry.size, depth.level)),
+                    OutputFormat::Jsonl => out.push_str(&format!("{}\n", json_file_entry(&canonical, entry.size, depth.level))),
                 }
             }
         }
     }
 
-    if depth != 0 {
+    if args.format == OutputFormat::Json && !new_json_entries.is_empty() {
+        state.json_entries.lock().unwrap().extend(new_json_entries);
+    }
+
+    if !out.is_empty() {
+        let _lock = state.stdout_lock.lock().unwrap();
+        print!("{out}");
+    }
+
+    state.files.fetch_add(local_files, Ordering::SeqCst);
+    state.file_size_sum.fetch_add(local_bytes, Ordering::SeqCst);
+    state.directories.fetch_add(local_dirs, Ordering::SeqCst);
+
+    if depth.remaining != 0 {
         if let Ok(read_dir) = current_path.read_dir() {
-            for path in read_dir.filter_map(Result::ok).map(|ent| ent.path()).filter(|path| path.is_dir()).filter(|path| args.all || path.file_name().unwrap().as_encoded_bytes()[0] != b'.').filter(|path| !path.is_symlink()) {
-                let res = dir_cmd_recursive(args, path, file_pattern, directories_only, depth - 1);
-                files += res.0;
-                file_size_sum += res.1;
-                directories += res.2;
+            let mut children = Vec::new();
+
+            for path in read_dir.filter_map(Result::ok).map(|ent| ent.path()).filter(|path| path.is_dir()).filter(|path| args.all || path.file_name().unwrap().as_encoded_bytes()[0] != b'.') {
+                let is_symlink = path.is_symlink();
+                if is_symlink && !args.follow {
+                    continue;
+                }
+
+                let Some(id) = dir_identity(&path) else { continue };
+                if !state.visited.lock().unwrap().insert(id) {
+                    if is_symlink && !args.bare && !args.quiet && args.format == OutputFormat::Text {
+                        let _lock = state.stdout_lock.lock().unwrap();
+                        println!("cycle skipped: {}", path.display());
+                    }
+                    continue;
+                }
+
+                children.push((path, Depth { remaining: depth.remaining - 1, level: depth.level + 1 }));
+            }
+
+            if !children.is_empty() {
+                state.pending.fetch_add(children.len(), Ordering::SeqCst);
+                let mut stack = stack.lock().unwrap();
+                // Pushed in reverse so the first-discovered child is popped
+                // next, keeping sibling order the same as a plain recursive
+                // walk while still processing one subtree at a time.
+                stack.extend(children.into_iter().rev());
             }
         }
     }
+}
 
-    (files, file_size_sum, directories)
+fn print_end_stats(files: usize, file_size_sum: usize, directories: usize, human: bool) {
+    print!("\t\t{} File(s)\t{}\n\t\t{} Dir(s)\n", files, format_size(file_size_sum, human), directories);
 }
 
-fn print_end_stats(files: usize, file_size_sum: usize, directories: usize) {
-    print!("\t\t{} File(s)\t{} bytes\n\t\t{} Dir(s)\n", files, file_size_sum, directories);
+/// Runs each of `ls`/`find`'s one-shot listings and, unless `--bare` or a
+/// JSON `--format` is in effect, prints the usual trailing stats line.
+fn print_shell_listing(session: &Args, current: &Path, pattern: &PathBuf, directories_only: bool, depth: usize) {
+    let stats = dir_cmd_recursive(session, current.to_path_buf(), pattern, directories_only, depth);
+    if !session.bare && session.format == OutputFormat::Text {
+        print_end_stats(stats.0, stats.1, stats.2, session.human);
+    }
+}
+
+/// Interactive `dirl:/path>` shell for browsing the tree that `dir_cmd_recursive`
+/// would otherwise list in one shot. Seeds its session state from `Args` but
+/// lets `all`/`bare`/`raw` be toggled without affecting the CLI invocation.
+fn run_shell(args: &Args, start_path: PathBuf, file_pattern: PathBuf, directories_only: bool) {
+    let mut session = args.clone();
+    let mut current = start_path;
+
+    loop {
+        print!("dirl:{}> ", current.display());
+        if io::stdout().flush().is_err() {
+            break;
+        }
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, char::is_whitespace);
+        let cmd = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim();
+
+        match cmd {
+            "exit" | "quit" => break,
+            "pwd" => println!("{}", current.display()),
+            "cd" => {
+                if rest.is_empty() {
+                    println!("usage: cd <dir>");
+                    continue;
+                }
+                let target = current.join(rest);
+                if target.is_dir() && target.read_dir().is_ok() {
+                    current = target;
+                } else {
+                    println!("cd: not a directory: {rest}");
+                }
+            }
+            "ls" => print_shell_listing(&session, &current, &file_pattern, directories_only, 0),
+            "find" => {
+                if rest.is_empty() {
+                    println!("usage: find <glob>");
+                    continue;
+                }
+                let (pattern, find_directories_only) = if rest.ends_with('.') {
+                    let mut pattern = rest.to_string();
+                    pattern.pop();
+                    (PathBuf::from(pattern), true)
+                } else {
+                    (PathBuf::from(rest), false)
+                };
+                print_shell_listing(&session, &current, &pattern, find_directories_only, session.depth);
+            }
+            "all" => {
+                session.all = !session.all;
+                println!("all: {}", session.all);
+            }
+            "bare" => {
+                session.bare = !session.bare;
+                println!("bare: {}", session.bare);
+            }
+            "raw" => {
+                session.raw = !session.raw;
+                println!("raw: {}", session.raw);
+            }
+            _ => println!("unknown command: {cmd}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn unique_temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("dirl-rs-test-{label}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn format_size_human_and_raw() {
+        assert_eq!(format_size(512, false), "512 bytes");
+        assert_eq!(format_size(1536, true), "1.5 KiB");
+    }
+
+    #[test]
+    fn subtree_size_excludes_hidden_unless_all() {
+        let dir = unique_temp_dir("subtree");
+        fs::write(dir.join(".hidden"), vec![0u8; 5000]).unwrap();
+        fs::write(dir.join("visible.txt"), vec![0u8; 100]).unwrap();
+
+        let args = Args::parse_from(["dirl-rs"]);
+        assert_eq!(subtree_size(&args, Path::new("*"), &dir), 100);
+
+        let args_all = Args::parse_from(["dirl-rs", "--all"]);
+        assert_eq!(subtree_size(&args_all, Path::new("*"), &dir), 5100);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_file_entry_reports_the_depth_it_is_given() {
+        let path = Path::new("/tmp/whatever/file.txt");
+        let at_level_two = json_file_entry(path, 123, 2);
+        assert!(at_level_two.contains("\"depth\":2"));
+        assert!(!at_level_two.contains(&usize::MAX.to_string()));
+    }
+
+    #[test]
+    fn dir_identity_matches_through_a_symlink() {
+        let dir = unique_temp_dir("cycle");
+        let target = dir.join("target");
+        fs::create_dir(&target).unwrap();
+
+        #[cfg(unix)]
+        {
+            let link = dir.join("link");
+            std::os::unix::fs::symlink(&dir, &link).unwrap();
+            // The self-referential symlink must resolve to the same identity
+            // as the directory it points at, which is what lets the cycle
+            // detector recognize it as already visited.
+            assert_eq!(dir_identity(&link), dir_identity(&dir));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }